@@ -0,0 +1,137 @@
+//! CDN-backed chunk storage for RMAN manifests. Kept behind the `download` cargo feature so the
+//! core parser stays dependency-light for callers who only read local manifests/bundles.
+use crate::rman::{self, ChunkStore, File, FileEntry};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// Errors produced while extracting a file from a CDN-backed manifest.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("manifest does not contain a file with id {0}")]
+    UnknownFile(u64),
+    #[error("CDN request failed: {0}")]
+    Download(#[from] Box<ureq::Error>),
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to assemble file: {0}")]
+    Chunk(#[from] rman::ChunkError),
+}
+
+/// A [`ChunkStore`] that fetches bundles from Riot's patch CDN on demand, keyed by bundle id, and
+/// caches them to a local directory so repeated reads from the same bundle only hit the network
+/// once.
+pub struct CdnChunkStore {
+    base_url: String,
+    cache_dir: PathBuf,
+    chunk_offsets: HashMap<u64, (u64, u32, u32)>,
+    bundles: RefCell<HashMap<u64, Vec<u8>>>,
+}
+
+impl CdnChunkStore {
+    pub fn new(base_url: impl Into<String>, cache_dir: impl Into<PathBuf>, file: &File) -> Self {
+        CdnChunkStore {
+            base_url: base_url.into(),
+            cache_dir: cache_dir.into(),
+            chunk_offsets: file.chunk_offsets(),
+            bundles: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn bundle_bytes(&self, bundle_id: u64) -> Result<Vec<u8>, Error> {
+        if let Some(cached) = self.bundles.borrow().get(&bundle_id) {
+            return Ok(cached.clone());
+        }
+
+        let cache_path = self.cache_dir.join(format!("{:016X}.bundle", bundle_id));
+        let data = if cache_path.exists() {
+            fs::read(&cache_path)?
+        } else {
+            let url = format!("{}/bundles/{:016X}.bundle", self.base_url, bundle_id);
+            let mut body = ureq::get(&url).call().map_err(Box::new)?.into_reader();
+            let mut buf = Vec::new();
+            body.read_to_end(&mut buf)?;
+
+            fs::create_dir_all(&self.cache_dir)?;
+            fs::write(&cache_path, &buf)?;
+
+            buf
+        };
+
+        self.bundles.borrow_mut().insert(bundle_id, data.clone());
+        Ok(data)
+    }
+}
+
+impl ChunkStore for CdnChunkStore {
+    fn chunk_bytes(&self, chunk_id: u64) -> Result<Vec<u8>, rman::ChunkError> {
+        let (bundle_id, offset, size) =
+            *self.chunk_offsets.get(&chunk_id).ok_or_else(|| format!("unknown chunk id {:016x}", chunk_id))?;
+        let bundle = self.bundle_bytes(bundle_id)?;
+        Ok(bundle[offset as usize..(offset + size) as usize].to_vec())
+    }
+}
+
+/// A [`ChunkStore`] that fetches only the bytes of requested chunks via HTTP range requests
+/// against their owning bundle, rather than downloading whole bundles up front. Compressed chunk
+/// bytes are cached by `chunk_id`, so a chunk shared across multiple files is only ever fetched
+/// once.
+pub struct RangeChunkStore {
+    base_url: String,
+    chunk_offsets: HashMap<u64, (u64, u32, u32)>,
+    cache: RefCell<HashMap<u64, Vec<u8>>>,
+}
+
+impl RangeChunkStore {
+    pub fn new(base_url: impl Into<String>, file: &File) -> Self {
+        RangeChunkStore { base_url: base_url.into(), chunk_offsets: file.chunk_offsets(), cache: RefCell::new(HashMap::new()) }
+    }
+}
+
+impl ChunkStore for RangeChunkStore {
+    fn chunk_bytes(&self, chunk_id: u64) -> Result<Vec<u8>, rman::ChunkError> {
+        if let Some(cached) = self.cache.borrow().get(&chunk_id) {
+            return Ok(cached.clone());
+        }
+
+        let (bundle_id, offset, size) =
+            *self.chunk_offsets.get(&chunk_id).ok_or_else(|| format!("unknown chunk id {:016x}", chunk_id))?;
+        let url = format!("{}/bundles/{:016X}.bundle", self.base_url, bundle_id);
+        let range = format!("bytes={}-{}", offset, offset + size - 1);
+        let mut body = ureq::get(&url).set("Range", &range).call().map_err(Box::new).map_err(Error::Download)?.into_reader();
+
+        let mut data = Vec::with_capacity(size as usize);
+        body.read_to_end(&mut data).map_err(Error::Io)?;
+
+        self.cache.borrow_mut().insert(chunk_id, data.clone());
+        Ok(data)
+    }
+}
+
+/// Resolves `file_id` in `file` and reconstructs its bytes by range-fetching and decompressing
+/// each of its chunks through `store`. Reuse the same `store` across multiple calls (e.g. several
+/// files from the same manifest) so chunks shared between them are only ever fetched once.
+pub fn extract_file(file: &File, file_id: u64, store: &RangeChunkStore) -> Result<Vec<u8>, Error> {
+    let entry = file.files().iter().find(|f| f.id() == file_id).ok_or(Error::UnknownFile(file_id))?;
+    Ok(rman::assemble(entry, store)?)
+}
+
+/// Like [`extract_file`], but writes the reconstructed bytes into `out` instead of returning them.
+/// The whole file is still assembled in memory first and written with a single `write_all`.
+pub fn extract_file_to<W: Write>(file: &File, file_id: u64, store: &RangeChunkStore, out: &mut W) -> Result<(), Error> {
+    let data = extract_file(file, file_id, store)?;
+    out.write_all(&data)?;
+    Ok(())
+}
+
+/// Selects the subset of a manifest's files whose resolved path starts with `prefix`.
+pub fn select_by_path_prefix<'a>(file: &'a File, prefix: &str) -> Vec<&'a FileEntry> {
+    file.files().iter().filter(|entry| file.resolve_path(entry).starts_with(prefix)).collect()
+}
+
+/// Selects the subset of a manifest's files whose language mask includes `language_id`.
+pub fn select_by_language(file: &File, language_id: u8) -> Vec<&FileEntry> {
+    file.files().iter().filter(|entry| entry.matches_language(language_id)).collect()
+}
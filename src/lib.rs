@@ -0,0 +1,13 @@
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+extern crate strum_macros;
+extern crate strum;
+
+mod macros;
+pub mod rman;
+pub mod wad;
+
+#[cfg(feature = "download")]
+pub mod download;
@@ -1,22 +1,14 @@
-mod macros;
-mod rman;
-mod wad;
-
-#[macro_use]
-extern crate serde_derive;
 extern crate serde_json;
 
-#[macro_use]
-extern crate strum_macros;
-extern crate strum;
+use rusty_dragon::wad;
 
 fn main() {
     let data = std::fs::read("C:\\cdragon\\cdragon\\wad\\Ahri.wad.client").unwrap();
-    let result = wad::parse(&data);
+    let result = wad::parse(&data).unwrap();
     println!("{}", serde_json::to_string_pretty(&result).unwrap());
     /*
         let data = std::fs::read("C:\\cdragon\\cdragon\\rman\\DC9F6F78A04934D6.manifest").unwrap();
-        let result = rman::parse(&data);
+        let result = rusty_dragon::rman::parse(&data).unwrap();
         println!("{}", serde_json::to_string_pretty(&result).unwrap());
     */
 }
@@ -1,15 +1,13 @@
-use nom::{
-    bytes::complete::tag,
-    error::VerboseError,
-    number::complete::{le_i32, le_u16, le_u32, le_u64, le_u8},
-    sequence::tuple,
-};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct File {
     header: Header,
     body: Body,
+    /// `Directory.id` -> index into `body.directories`, built once so path resolution doesn't
+    /// re-collect a `HashMap` from every directory on every call.
+    #[serde(skip)]
+    dir_index: HashMap<u64, usize>,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
@@ -40,20 +38,20 @@ struct Bundle {
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
-struct Chunk {
+pub struct Chunk {
     compressed_size: u32,
     uncompressed_size: u32,
     chunk_id: u64,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
-struct Language {
+pub struct Language {
     id: u8,
     name: String,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
-struct FileEntry {
+pub struct FileEntry {
     id: u64,
     name: String,
     symlink: String,
@@ -78,251 +76,897 @@ struct Body {
     directories: Vec<Directory>,
 }
 
-pub fn parse(input: &[u8]) -> File {
-    let header = header(input);
+impl File {
+    fn from_parts(header: Header, body: Body) -> File {
+        let dir_index = body.directories.iter().enumerate().map(|(index, dir)| (dir.id, index)).collect();
+        File { header, body, dir_index }
+    }
+
+    /// Looks up a directory by id via the cached `dir_index`, in O(1) rather than scanning
+    /// `body.directories`.
+    fn directory(&self, id: u64) -> Option<&Directory> {
+        self.dir_index.get(&id).map(|&index| &self.body.directories[index])
+    }
+
+    /// Walks `directory_id` / `parent_id` links up to the root and joins them with `file.name`
+    /// into a full slash-separated path. Guards against cycles and dangling parent ids by simply
+    /// stopping the walk there, so a malformed manifest yields a partial path rather than hanging.
+    pub fn resolve_path(&self, file: &FileEntry) -> String {
+        let mut parts = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = self.directory(file.directory_id);
+
+        while let Some(dir) = current {
+            if !visited.insert(dir.id) {
+                break;
+            }
+
+            parts.push(dir.name.as_str());
+            current = if dir.parent_id == dir.id { None } else { self.directory(dir.parent_id) };
+        }
+
+        parts.reverse();
+        parts.push(file.name.as_str());
+        parts.join("/")
+    }
+
+    /// Resolves `file_id` to its full slash-separated path via [`resolve_path`](File::resolve_path),
+    /// or `None` if the manifest has no file with that id.
+    pub fn full_path(&self, file_id: u64) -> Option<String> {
+        let entry = self.body.files.iter().find(|entry| entry.id == file_id)?;
+        Some(self.resolve_path(entry))
+    }
+
+    /// Lists every file directly contained in `directory_id` (not recursive into subdirectories).
+    pub fn list_dir(&self, directory_id: u64) -> Vec<&FileEntry> {
+        self.body.files.iter().filter(|entry| entry.directory_id == directory_id).collect()
+    }
+
+    /// Iterates every file in the manifest paired with its resolved full path.
+    pub fn walk(&self) -> impl Iterator<Item = (String, &FileEntry)> {
+        self.body.files.iter().map(move |entry| (self.resolve_path(entry), entry))
+    }
+
+    /// Resolves every language bit set in `entry.language_mask()` to its `Language` entry.
+    pub fn languages_for(&self, entry: &FileEntry) -> Vec<&Language> {
+        self.body.languages.iter().filter(|language| entry.matches_language(language.id)).collect()
+    }
+
+    /// Selects every file whose language mask includes `language_id`.
+    pub fn files_for_language(&self, language_id: u8) -> Vec<&FileEntry> {
+        self.body.files.iter().filter(|entry| entry.matches_language(language_id)).collect()
+    }
+
+    /// Like [`files_for_language`](File::files_for_language), but looks the id up by language
+    /// name first. Combine with [`resolve_path`](File::resolve_path) to extract just the assets
+    /// for a chosen locale.
+    pub fn files_for_language_name(&self, name: &str) -> Vec<&FileEntry> {
+        match self.body.languages.iter().find(|language| language.name == name) {
+            Some(language) => self.files_for_language(language.id),
+            None => Vec::new(),
+        }
+    }
+
+    /// Maps every `chunk_id` across all bundles to `(bundle_id, offset_in_bundle, compressed_size)`.
+    pub fn chunk_offsets(&self) -> HashMap<u64, (u64, u32, u32)> {
+        let mut offsets = HashMap::new();
+
+        for bundle in &self.body.bundles {
+            let mut offset = 0u32;
+            for chunk in &bundle.chunks {
+                offsets.insert(chunk.chunk_id, (bundle.bundle_id, offset, chunk.compressed_size));
+                offset += chunk.compressed_size;
+            }
+        }
+
+        offsets
+    }
+
+    pub fn files(&self) -> &[FileEntry] {
+        &self.body.files
+    }
+
+    pub fn languages(&self) -> &[Language] {
+        &self.body.languages
+    }
+
+    /// Looks up a chunk's metadata by its id, searching every bundle. Used to verify downloaded
+    /// chunk data against the size/id the manifest declares for it.
+    pub fn chunk(&self, chunk_id: u64) -> Option<&Chunk> {
+        self.body.bundles.iter().flat_map(|bundle| &bundle.chunks).find(|chunk| chunk.chunk_id == chunk_id)
+    }
+
+    /// Serializes this manifest back to bytes. See [`write`].
+    pub fn serialize(&self) -> Vec<u8> {
+        write(self)
+    }
+}
+
+impl FileEntry {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn chunk_ids(&self) -> &[u64] {
+        &self.chunk_ids
+    }
+
+    pub fn language_mask(&self) -> u32 {
+        self.language
+    }
+
+    /// A mask of `0` means the file isn't tied to any particular locale (e.g. code or shared
+    /// data), as opposed to a file whose mask has no bits set that match any known `Language`.
+    pub fn is_language_neutral(&self) -> bool {
+        self.language == 0
+    }
+
+    /// Whether this file's language mask includes `language_id`. `language_id >= 32` can never
+    /// match, since the mask is only 32 bits wide; this just returns `false` for those rather
+    /// than overflowing the shift.
+    pub fn matches_language(&self, language_id: u8) -> bool {
+        1u32.checked_shl(language_id as u32).is_some_and(|bit| self.language & bit != 0)
+    }
+}
+
+impl Language {
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// A boxed error from fetching chunk bytes — network, disk, or lookup failures across chunk
+/// store backends.
+pub type ChunkError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Supplies the raw, still zstd-compressed bytes for a single chunk. Implementations may serve
+/// them from a local bundle blob already in memory or fetch them from a CDN on demand, so the
+/// same [`assemble`] code path works either way.
+pub trait ChunkStore {
+    fn chunk_bytes(&self, chunk_id: u64) -> Result<Vec<u8>, ChunkError>;
+}
+
+/// Reconstructs a file's full bytes by decompressing each of its chunks (in `chunk_ids` order)
+/// and concatenating them, verifying the running total matches the file's `size`.
+pub fn assemble(file: &FileEntry, chunk_data: &dyn ChunkStore) -> Result<Vec<u8>, ChunkError> {
+    let mut out = Vec::with_capacity(file.size as usize);
+
+    for chunk_id in &file.chunk_ids {
+        let compressed = chunk_data.chunk_bytes(*chunk_id)?;
+        let decompressed = zstd::decode_all(compressed.as_slice())?;
+        out.extend_from_slice(&decompressed);
+    }
+
+    assert_eq!(out.len() as u32, file.size, "assembled size for file {} does not match manifest size", file.id);
+
+    Ok(out)
+}
+
+/// Checks that `data` is the genuine uncompressed content of `chunk`: the RMAN format defines
+/// `chunk_id` as the XXH64 hash of the chunk's uncompressed bytes, so a corrupt or truncated
+/// download will fail either the length or the hash comparison.
+pub fn verify_chunk(chunk: &Chunk, data: &[u8]) -> bool {
+    data.len() as u32 == chunk.uncompressed_size && xxhash_rust::xxh64::xxh64(data, 0) == chunk.chunk_id
+}
+
+/// Why [`verify_file`] failed: a chunk couldn't be fetched or decompressed, the manifest declares
+/// a chunk id that isn't present in any bundle, or a fetched chunk didn't match its declared id
+/// (the value is the chunk id that failed).
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyFileError {
+    #[error("failed to fetch or decompress chunk data: {0}")]
+    Fetch(#[from] ChunkError),
+    #[error("chunk {0:016x} is referenced by the file entry but missing from the manifest")]
+    UnknownChunk(u64),
+    #[error("chunk {0:016x} failed verification")]
+    Mismatch(u64),
+}
+
+/// Reconstructs every chunk of `entry` from `chunk_data` (mirroring [`assemble`]) and checks each
+/// one with [`verify_chunk`], stopping at the first chunk that fails to fetch, decompress, or
+/// verify.
+pub fn verify_file(file: &File, entry: &FileEntry, chunk_data: &dyn ChunkStore) -> Result<(), VerifyFileError> {
+    for chunk_id in &entry.chunk_ids {
+        let compressed = chunk_data.chunk_bytes(*chunk_id)?;
+        let decompressed = zstd::decode_all(compressed.as_slice()).map_err(ChunkError::from)?;
+
+        let chunk = file.chunk(*chunk_id).ok_or(VerifyFileError::UnknownChunk(*chunk_id))?;
+        if !verify_chunk(chunk, &decompressed) {
+            return Err(VerifyFileError::Mismatch(*chunk_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Result of [`diff`]: how files changed between two manifest versions, and which chunks a
+/// downloader needs to fetch to move from the old version to the new one, grouped by the bundle
+/// that holds them.
+#[derive(Debug, Default)]
+pub struct ManifestDiff {
+    pub added: Vec<u64>,
+    pub removed: Vec<u64>,
+    pub modified: Vec<u64>,
+    pub added_bytes: u64,
+    pub removed_bytes: u64,
+    /// New chunk ids grouped by the id of the bundle (in `new`) that holds them.
+    pub new_chunks_by_bundle: HashMap<u64, Vec<u64>>,
+    /// Total uncompressed size of every chunk in `new_chunks_by_bundle`. Chunks are fetched
+    /// compressed, so this is not the number of bytes transferred from the CDN.
+    pub download_bytes: u64,
+}
+
+/// Compares two manifest versions by `FileEntry.id`, classifying each file as added, removed, or
+/// modified (its `chunk_ids` or `size` changed), and computes the set of chunks present in `new`
+/// but absent from `old`, grouped by owning bundle, so a downloader knows exactly what to fetch.
+pub fn diff(old: &File, new: &File) -> ManifestDiff {
+    let mut result = ManifestDiff::default();
+
+    let old_by_id: HashMap<u64, &FileEntry> = old.body.files.iter().map(|entry| (entry.id, entry)).collect();
+    let new_by_id: HashMap<u64, &FileEntry> = new.body.files.iter().map(|entry| (entry.id, entry)).collect();
+
+    for (id, new_entry) in &new_by_id {
+        match old_by_id.get(id) {
+            None => {
+                result.added.push(*id);
+                result.added_bytes += new_entry.size as u64;
+            }
+            Some(old_entry) => {
+                if old_entry.chunk_ids != new_entry.chunk_ids || old_entry.size != new_entry.size {
+                    result.modified.push(*id);
+                }
+            }
+        }
+    }
+
+    for (id, old_entry) in &old_by_id {
+        if !new_by_id.contains_key(id) {
+            result.removed.push(*id);
+            result.removed_bytes += old_entry.size as u64;
+        }
+    }
+
+    let old_chunk_ids: HashSet<u64> = old.body.bundles.iter().flat_map(|bundle| &bundle.chunks).map(|chunk| chunk.chunk_id).collect();
+
+    for bundle in &new.body.bundles {
+        for chunk in &bundle.chunks {
+            if !old_chunk_ids.contains(&chunk.chunk_id) {
+                result.new_chunks_by_bundle.entry(bundle.bundle_id).or_default().push(chunk.chunk_id);
+                result.download_bytes += chunk.uncompressed_size as u64;
+            }
+        }
+    }
+
+    result
+}
+
+/// A [`ChunkStore`] backed by bundle blobs already held in memory (e.g. bundle files read from
+/// disk). Indexes every chunk to its owning bundle id and byte offset once at construction time.
+pub struct LocalChunkStore<'a> {
+    bundles: HashMap<u64, &'a [u8]>,
+    chunk_offsets: HashMap<u64, (u64, u32, u32)>,
+}
+
+impl<'a> LocalChunkStore<'a> {
+    pub fn new(file: &File, bundles: HashMap<u64, &'a [u8]>) -> Self {
+        LocalChunkStore { bundles, chunk_offsets: file.chunk_offsets() }
+    }
+}
+
+impl<'a> ChunkStore for LocalChunkStore<'a> {
+    fn chunk_bytes(&self, chunk_id: u64) -> Result<Vec<u8>, ChunkError> {
+        let (bundle_id, offset, size) =
+            *self.chunk_offsets.get(&chunk_id).ok_or_else(|| format!("unknown chunk id {:016x}", chunk_id))?;
+        let data = *self.bundles.get(&bundle_id).ok_or_else(|| format!("bundle {:016x} blob not loaded", bundle_id))?;
+        Ok(data[offset as usize..(offset + size) as usize].to_vec())
+    }
+}
+
+/// Errors produced while parsing an RMAN manifest.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("input does not start with the RMAN magic")]
+    InvalidMagic,
+    #[error("input is too short to contain a complete RMAN header")]
+    Truncated,
+    #[error("failed to decompress manifest body: {0}")]
+    Decompress(#[from] std::io::Error),
+}
+
+fn read_u8(input: &[u8], pos: u32) -> Result<u8, Error> {
+    input.get(pos as usize).copied().ok_or(Error::Truncated)
+}
+
+fn read_u16(input: &[u8], pos: u32) -> Result<u16, Error> {
+    let pos = pos as usize;
+    let bytes: [u8; 2] = input.get(pos..pos + 2).ok_or(Error::Truncated)?.try_into().unwrap();
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_i32(input: &[u8], pos: u32) -> Result<i32, Error> {
+    let pos = pos as usize;
+    let bytes: [u8; 4] = input.get(pos..pos + 4).ok_or(Error::Truncated)?.try_into().unwrap();
+    Ok(i32::from_le_bytes(bytes))
+}
+
+fn read_u32(input: &[u8], pos: u32) -> Result<u32, Error> {
+    let pos = pos as usize;
+    let bytes: [u8; 4] = input.get(pos..pos + 4).ok_or(Error::Truncated)?.try_into().unwrap();
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(input: &[u8], pos: u32) -> Result<u64, Error> {
+    let pos = pos as usize;
+    let bytes: [u8; 8] = input.get(pos..pos + 8).ok_or(Error::Truncated)?.try_into().unwrap();
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Reads a length-prefixed UTF-8 string (the encoding `write_string` produces) at `pos`.
+fn read_str(input: &[u8], pos: u32) -> Result<String, Error> {
+    let len = read_u32(input, pos)?;
+    let start = pos as usize + 4;
+    let bytes = input.get(start..start + len as usize).ok_or(Error::Truncated)?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+pub fn parse(input: &[u8]) -> Result<File, Error> {
+    if input.len() < 4 {
+        return Err(Error::Truncated);
+    }
+    if &input[0..4] != b"RMAN" {
+        return Err(Error::InvalidMagic);
+    }
+    if input.len() < 21 {
+        return Err(Error::Truncated);
+    }
+
+    let header = header(input)?;
+
+    if input.len() < (header.offset + header.length) as usize {
+        return Err(Error::Truncated);
+    }
 
     let body_data = &input[header.offset as usize..(header.offset + header.length) as usize];
 
-    let decompressed = zstd::decode_all(body_data).unwrap();
+    let decompressed = zstd::decode_all(body_data)?;
 
-    let offsets = offset_map(&decompressed);
-    let bundles = bundles(&decompressed, offsets.bundle_offset);
-    let languages = languages(&decompressed, offsets.language_offset);
-    let directories = directories(&decompressed, offsets.folder_offset);
-    let files = files(&decompressed, offsets.file_offset);
+    let offsets = offset_map(&decompressed)?;
+    let bundles = bundles(&decompressed, offsets.bundle_offset)?;
+    let languages = languages(&decompressed, offsets.language_offset)?;
+    let directories = directories(&decompressed, offsets.folder_offset)?;
+    let files = files(&decompressed, offsets.file_offset)?;
 
     let body = Body { bundles, languages, files, directories };
-    File { header, body }
+    Ok(File::from_parts(header, body))
+}
+
+fn patch_u32(buf: &mut [u8], pos: u32, value: u32) {
+    buf[pos as usize..pos as usize + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn patch_i32(buf: &mut [u8], pos: u32, value: i32) {
+    buf[pos as usize..pos as usize + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Appends a length-prefixed UTF-8 string block and returns its absolute position.
+fn write_string(buf: &mut Vec<u8>, s: &str) -> u32 {
+    let pos = buf.len() as u32;
+    buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    buf.extend_from_slice(s.as_bytes());
+    pos
+}
+
+/// Appends a `count` + raw `u64` values vector (as used for `FileEntry.chunk_ids`) and returns
+/// its absolute position.
+fn write_long_vector(buf: &mut Vec<u8>, values: &[u64]) -> u32 {
+    let pos = buf.len() as u32;
+    buf.extend_from_slice(&(values.len() as u32).to_le_bytes());
+    for value in values {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+    pos
+}
+
+/// Appends a vtable-addressed vector: a `count` followed by per-element uoffsets, writing each
+/// element (via `write_item`) right after the uoffset table and patching its slot once the
+/// element's absolute position is known. Mirrors [`parse_vector`] in reverse.
+fn write_vector<T>(buf: &mut Vec<u8>, items: &[T], mut write_item: impl FnMut(&mut Vec<u8>, &T) -> u32) -> u32 {
+    let vector_start = buf.len() as u32;
+    buf.extend_from_slice(&(items.len() as u32).to_le_bytes());
+
+    let slot_start = buf.len() as u32;
+    for _ in items {
+        buf.extend_from_slice(&0u32.to_le_bytes());
+    }
+
+    for (i, item) in items.iter().enumerate() {
+        let entry_pos = write_item(buf, item);
+        let slot_pos = slot_start + 4 * i as u32;
+        patch_u32(buf, slot_pos, entry_pos - slot_pos);
+    }
+
+    vector_start
+}
+
+fn write_chunk(buf: &mut Vec<u8>, chunk: &Chunk) -> u32 {
+    let table_start = buf.len() as u32;
+    buf.extend_from_slice(&0i32.to_le_bytes()); // vtable backoffset, patched below
+    buf.extend_from_slice(&chunk.chunk_id.to_le_bytes()); // @4
+    buf.extend_from_slice(&chunk.compressed_size.to_le_bytes()); // @12
+    buf.extend_from_slice(&chunk.uncompressed_size.to_le_bytes()); // @16
+
+    let vtable_start = buf.len() as u32;
+    // parts: ["unknown1", "unknown2", "chunk_id", "compressed_size", "uncompressed_size"]
+    for value in [0u16, 0, 4, 12, 16] {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    patch_i32(buf, table_start, table_start as i32 - vtable_start as i32);
+    table_start
+}
+
+fn write_bundle(buf: &mut Vec<u8>, bundle: &Bundle) -> u32 {
+    let table_start = buf.len() as u32;
+    buf.extend_from_slice(&0i32.to_le_bytes()); // vtable backoffset, patched below
+    buf.extend_from_slice(&bundle.bundle_id.to_le_bytes()); // @4
+    let chunks_rel_offset = buf.len() as u32 - table_start;
+
+    write_vector(buf, &bundle.chunks, write_chunk);
+
+    let vtable_start = buf.len() as u32;
+    // parts: ["bundle_id", "chunks", "unknown", "header_size"]
+    for value in [4u16, chunks_rel_offset as u16, 0, 0] {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    patch_i32(buf, table_start, table_start as i32 - vtable_start as i32);
+    table_start
+}
+
+fn write_language(buf: &mut Vec<u8>, language: &Language) -> u32 {
+    let table_start = buf.len() as u32;
+    buf.extend_from_slice(&0i32.to_le_bytes()); // vtable backoffset, patched below
+    buf.extend_from_slice(&0u32.to_le_bytes()); // @4 name_offset slot, patched below
+    buf.push(language.id); // @8
+    buf.extend_from_slice(&[0u8; 3]); // padding
+
+    let name_slot_pos = table_start + 4;
+    let name_pos = write_string(buf, &language.name);
+    patch_u32(buf, name_slot_pos, name_pos - name_slot_pos);
+
+    let vtable_start = buf.len() as u32;
+    // parts: ["name_offset", "unknown1", "language_id"]
+    for value in [4u16, 0, 8] {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    patch_i32(buf, table_start, table_start as i32 - vtable_start as i32);
+    table_start
+}
+
+fn write_directory(buf: &mut Vec<u8>, directory: &Directory) -> u32 {
+    let table_start = buf.len() as u32;
+    buf.extend_from_slice(&0i32.to_le_bytes()); // vtable backoffset, patched below
+    buf.extend_from_slice(&directory.id.to_le_bytes()); // @4
+    buf.extend_from_slice(&directory.parent_id.to_le_bytes()); // @12
+    buf.extend_from_slice(&0u32.to_le_bytes()); // @20 name_offset slot, patched below
+
+    let name_slot_pos = table_start + 20;
+    let name_pos = write_string(buf, &directory.name);
+    patch_u32(buf, name_slot_pos, name_pos - name_slot_pos);
+
+    let vtable_start = buf.len() as u32;
+    // parts: ["unknown1", "unknown2", "directory_id", "parent_id", "name_offset"]
+    for value in [0u16, 0, 4, 12, 20] {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    patch_i32(buf, table_start, table_start as i32 - vtable_start as i32);
+    table_start
+}
+
+fn write_file_entry(buf: &mut Vec<u8>, file: &FileEntry) -> u32 {
+    let table_start = buf.len() as u32;
+    buf.extend_from_slice(&0i32.to_le_bytes()); // vtable backoffset, patched below
+    buf.extend_from_slice(&file.id.to_le_bytes()); // @4
+    buf.extend_from_slice(&file.directory_id.to_le_bytes()); // @12
+    buf.extend_from_slice(&file.size.to_le_bytes()); // @20
+    buf.extend_from_slice(&file.language.to_le_bytes()); // @24
+    buf.extend_from_slice(&0u32.to_le_bytes()); // @28 name_offset slot, patched below
+    buf.extend_from_slice(&0u32.to_le_bytes()); // @32 symlink_offset slot, patched below
+    let chunks_rel_offset = buf.len() as u32 - table_start;
+
+    write_long_vector(buf, &file.chunk_ids);
+
+    let name_slot_pos = table_start + 28;
+    let name_pos = write_string(buf, &file.name);
+    patch_u32(buf, name_slot_pos, name_pos - name_slot_pos);
+
+    let symlink_slot_pos = table_start + 32;
+    let symlink_pos = write_string(buf, &file.symlink);
+    patch_u32(buf, symlink_slot_pos, symlink_pos - symlink_slot_pos);
+
+    let vtable_start = buf.len() as u32;
+    // parts: ["unknown1", "chunks", "file_id", "directory_id", "file_size", "name_offset",
+    //         "language_mask", "unknown2", "unknown3", "unknown4", "unknown5", "symlink_offset",
+    //         "unknown6", "unknown7", "unknown8"]
+    let values: [u16; 15] = [0, chunks_rel_offset as u16, 4, 12, 20, 28, 24, 0, 0, 0, 0, 32, 0, 0, 0];
+    for value in values {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    patch_i32(buf, table_start, table_start as i32 - vtable_start as i32);
+    table_start
+}
+
+/// Serializes `file` back to RMAN bytes: rebuilds the vtable-addressed body (bundle, language,
+/// file and directory tables, their string blobs, and the root `OffsetMap`), zstd-compresses it,
+/// and prepends a header with matching `offset`/`length`/`decompressed_length`. `parse(write(f))`
+/// reproduces `f`'s tables and its other header fields; `offset`, `length` and
+/// `decompressed_length` describe the newly written bytes.
+pub fn write(file: &File) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    body.extend_from_slice(&4u32.to_le_bytes()); // header_offset: root table starts right after this
+    let root_table_start = body.len() as u32;
+    for _ in 0..5 {
+        body.extend_from_slice(&0u32.to_le_bytes());
+    }
+
+    let bundles_pos = write_vector(&mut body, &file.body.bundles, write_bundle);
+    let languages_pos = write_vector(&mut body, &file.body.languages, write_language);
+    let directories_pos = write_vector(&mut body, &file.body.directories, write_directory);
+    let files_pos = write_vector(&mut body, &file.body.files, write_file_entry);
+
+    patch_u32(&mut body, root_table_start + 4, bundles_pos - (root_table_start + 4));
+    patch_u32(&mut body, root_table_start + 8, languages_pos - (root_table_start + 8));
+    patch_u32(&mut body, root_table_start + 12, files_pos - (root_table_start + 12));
+    patch_u32(&mut body, root_table_start + 16, directories_pos - (root_table_start + 16));
+
+    let compressed = zstd::encode_all(body.as_slice(), 0).unwrap();
+
+    let mut out = Vec::with_capacity(28 + compressed.len());
+    out.extend_from_slice(b"RMAN");
+    out.push(file.header.major);
+    out.push(file.header.minor);
+    out.push(file.header.unknown);
+    out.push(file.header.signature_type);
+    out.extend_from_slice(&28u32.to_le_bytes()); // offset
+    out.extend_from_slice(&(compressed.len() as u32).to_le_bytes()); // length
+    out.extend_from_slice(&file.header.manifest_id.to_le_bytes());
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes()); // decompressed_length
+    out.extend_from_slice(&compressed);
+
+    out
 }
 
-fn header(input: &[u8]) -> Header {
-    let (magic, major, minor, unknown, signature_type, offset, length, manifest_id, decompressed_length) =
-        crate::parse_tuple!((tag("RMAN"), le_u8, le_u8, le_u8, le_u8, le_u32, le_u32, le_u64, le_u32,), input);
+/// Why a single header integrity check failed.
+#[derive(Debug)]
+pub enum Mismatch {
+    /// The decompressed body length didn't match the header's `decompressed_length`.
+    DecompressedLength { expected: u32, actual: usize },
+    /// The header's `signature_type` isn't one of the recognized values (`0` = none, `1` = ecdsa).
+    UnknownSignatureType(u8),
+    /// `header.offset`/`header.length` overflow or extend past the end of the input bytes.
+    BodyOutOfBounds { offset: u32, length: u32, input_len: usize },
+    /// The body range failed to decompress as zstd.
+    Decompress(std::io::Error),
+}
+
+/// Result of [`verify`]: every check that passed and every one that didn't, keyed by `manifest_id`.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub ok: Vec<u64>,
+    pub failed: Vec<(u64, Mismatch)>,
+}
+
+/// Validates the integrity metadata the RMAN header records: that `offset`/`length` describe a
+/// zstd body within `input` whose decompressed size is exactly `decompressed_length`, and that
+/// `signature_type` is a recognized value. A malformed header surfaces as a failed check rather
+/// than panicking.
+pub fn verify(input: &[u8], file: &File) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    let id = file.header.manifest_id;
+
+    let body_data = file
+        .header
+        .offset
+        .checked_add(file.header.length)
+        .and_then(|end| input.get(file.header.offset as usize..end as usize));
+
+    match body_data.map(zstd::decode_all) {
+        Some(Ok(decompressed)) if decompressed.len() as u32 == file.header.decompressed_length => report.ok.push(id),
+        Some(Ok(decompressed)) => {
+            report.failed.push((id, Mismatch::DecompressedLength { expected: file.header.decompressed_length, actual: decompressed.len() }))
+        }
+        Some(Err(e)) => report.failed.push((id, Mismatch::Decompress(e))),
+        None => {
+            report.failed.push((id, Mismatch::BodyOutOfBounds { offset: file.header.offset, length: file.header.length, input_len: input.len() }))
+        }
+    }
+
+    match file.header.signature_type {
+        0 | 1 => report.ok.push(id),
+        other => report.failed.push((id, Mismatch::UnknownSignatureType(other))),
+    }
+
+    report
+}
 
-    Header {
-        magic: String::from_utf8_lossy(magic).into_owned(),
-        major,
-        minor,
-        unknown,
-        signature_type,
-        offset,
-        length,
-        manifest_id,
-        decompressed_length,
+fn header(input: &[u8]) -> Result<Header, Error> {
+    if input.get(0..4) != Some(b"RMAN".as_slice()) {
+        return Err(Error::InvalidMagic);
     }
+
+    Ok(Header {
+        magic: "RMAN".to_owned(),
+        major: read_u8(input, 4)?,
+        minor: read_u8(input, 5)?,
+        unknown: read_u8(input, 6)?,
+        signature_type: read_u8(input, 7)?,
+        offset: read_u32(input, 8)?,
+        length: read_u32(input, 12)?,
+        manifest_id: read_u64(input, 16)?,
+        decompressed_length: read_u32(input, 24)?,
+    })
 }
 
-fn offset_map(input: &[u8]) -> OffsetMap {
-    let header_offset = crate::parse_single!(le_u32, input);
+fn offset_map(input: &[u8]) -> Result<OffsetMap, Error> {
+    let header_offset = read_u32(input, 0)?;
+
+    Ok(OffsetMap {
+        bundle_offset: header_offset + read_u32(input, header_offset + 4)? + 4,
+        language_offset: header_offset + read_u32(input, header_offset + 8)? + 8,
+        file_offset: header_offset + read_u32(input, header_offset + 12)? + 12,
+        folder_offset: header_offset + read_u32(input, header_offset + 16)? + 16,
+    })
+}
 
-    let (_, bundle_offset, language_offset, file_offset, folder_offset) =
-        crate::parse_tuple!((le_u32, le_u32, le_u32, le_u32, le_u32,), &input[header_offset as usize..]);
+/// Reads an `N`-field vtable as a fixed array indexed by field position, avoiding the allocation
+/// and string hashing a `HashMap<String, u16>` per entry would cost. Callers index the result
+/// with one of the small field enums below instead of a field name.
+fn parse_vtable<const N: usize>(input: &[u8], table_offset: u32) -> Result<[u16; N], Error> {
+    let offset = read_i32(input, table_offset)?;
+    let vtable_start = (table_offset as i32 - offset) as u32;
 
-    OffsetMap {
-        bundle_offset: header_offset + bundle_offset + 4,
-        language_offset: header_offset + language_offset + 8,
-        file_offset: header_offset + file_offset + 12,
-        folder_offset: header_offset + folder_offset + 16,
+    let mut offsets = [0u16; N];
+    for (index, slot) in offsets.iter_mut().enumerate() {
+        *slot = read_u16(input, vtable_start + 2 * index as u32)?;
     }
+
+    Ok(offsets)
 }
 
-fn parse_vector<F>(input: &[u8], table_offset: u32, parts: &[&str], f: &mut F)
+fn parse_vector<F, const N: usize>(input: &[u8], table_offset: u32, f: &mut F) -> Result<(), Error>
 where
-    F: FnMut(u32, HashMap<String, u16>),
+    F: FnMut(u32, [u16; N]) -> Result<(), Error>,
 {
-    let count = crate::parse_single!(le_u32, &input[table_offset as usize..]);
+    let count = read_u32(input, table_offset)?;
 
     for i in 0..count {
         let entry_position = 4 + 4 * i;
-        let offset = crate::parse_single!(le_u32, &input[(table_offset + entry_position) as usize..]);
+        let offset = read_u32(input, table_offset + entry_position)?;
 
         let entry_data_offset = entry_position + offset + table_offset;
 
-        let entry_offsets = parse_vtable(input, entry_data_offset, parts);
-        f(entry_data_offset, entry_offsets);
+        let entry_offsets = parse_vtable::<N>(input, entry_data_offset)?;
+        f(entry_data_offset, entry_offsets)?;
     }
+
+    Ok(())
 }
 
-fn parse_long_vector<F>(input: &[u8], start_offset: u32, f: &mut F)
+fn parse_long_vector<F>(input: &[u8], start_offset: u32, f: &mut F) -> Result<(), Error>
 where
-    F: FnMut(u64),
+    F: FnMut(u64) -> Result<(), Error>,
 {
-    let count = crate::parse_single!(le_u32, &input[start_offset as usize..]);
+    let count = read_u32(input, start_offset)?;
 
     for i in 0..count {
         let entry_position = 4 + 8 * i;
-        let value = crate::parse_single!(le_u64, &input[(start_offset + entry_position) as usize..]);
-        f(value);
+        let value = read_u64(input, start_offset + entry_position)?;
+        f(value)?;
     }
+
+    Ok(())
 }
 
-fn parse_vtable(input: &[u8], table_offset: u32, entries: &[&str]) -> HashMap<String, u16> {
-    let offset = crate::parse_single!(le_i32, &input[table_offset as usize..]);
-    let vtable_data = &input[(table_offset as i32 - offset) as usize..];
+#[derive(Clone, Copy)]
+enum BundleField {
+    BundleId = 0,
+    Chunks = 1,
+}
 
-    let mut offsets = HashMap::<String, u16>::new();
-    for (index, &element) in entries.iter().enumerate() {
-        let value = crate::parse_single!(le_u16, &vtable_data[(index * 2) as usize..]);
-        offsets.insert(element.to_owned(), value);
-    }
+#[derive(Clone, Copy)]
+enum ChunkField {
+    ChunkId = 2,
+    CompressedSize = 3,
+    UncompressedSize = 4,
+}
 
-    offsets
+#[derive(Clone, Copy)]
+enum LanguageField {
+    NameOffset = 0,
+    LanguageId = 2,
 }
 
-fn bundles(input: &[u8], bundles_start: u32) -> Vec<Bundle> {
+#[derive(Clone, Copy)]
+enum DirectoryField {
+    DirectoryId = 2,
+    ParentId = 3,
+    NameOffset = 4,
+}
+
+#[derive(Clone, Copy)]
+enum FileField {
+    Chunks = 1,
+    FileId = 2,
+    DirectoryId = 3,
+    FileSize = 4,
+    NameOffset = 5,
+    LanguageMask = 6,
+    SymlinkOffset = 11,
+}
+
+fn bundles(input: &[u8], bundles_start: u32) -> Result<Vec<Bundle>, Error> {
     let mut bundles = Vec::<Bundle>::new();
 
-    let mut parse_single_bundle = |start_offset: u32, entry_offsets: HashMap<String, u16>| {
-        let bundle_id_offset = entry_offsets.get("bundle_id").unwrap().to_owned();
-        let bundle_id = crate::parse_single!(le_u64, &input[(start_offset + bundle_id_offset as u32) as usize..]);
+    let mut parse_single_bundle = |start_offset: u32, entry_offsets: [u16; 4]| {
+        let bundle_id = read_u64(input, start_offset + entry_offsets[BundleField::BundleId as usize] as u32)?;
 
         let mut chunks_list = Vec::<Chunk>::new();
-        let chunks_offset = entry_offsets.get("chunks").unwrap().to_owned();
-        let mut parse_single_chunk = |start_offset: u32, entry_offsets: HashMap<String, u16>| {
-            let chunk_id_offset = entry_offsets.get("chunk_id").unwrap().to_owned();
-            let chunk_id = crate::parse_single!(le_u64, &input[(start_offset + chunk_id_offset as u32) as usize..]);
-
-            let compressed_size_offset = entry_offsets.get("compressed_size").unwrap().to_owned();
-            let compressed_size = crate::parse_single!(le_u32, &input[(start_offset + compressed_size_offset as u32) as usize..]);
+        let chunks_offset = entry_offsets[BundleField::Chunks as usize] as u32;
 
-            let uncompressed_size_offset = entry_offsets.get("uncompressed_size").unwrap().to_owned();
-            let uncompressed_size = crate::parse_single!(le_u32, &input[(start_offset + uncompressed_size_offset as u32) as usize..]);
+        let mut parse_single_chunk = |start_offset: u32, entry_offsets: [u16; 5]| {
+            let chunk_id = read_u64(input, start_offset + entry_offsets[ChunkField::ChunkId as usize] as u32)?;
+            let compressed_size = read_u32(input, start_offset + entry_offsets[ChunkField::CompressedSize as usize] as u32)?;
+            let uncompressed_size = read_u32(input, start_offset + entry_offsets[ChunkField::UncompressedSize as usize] as u32)?;
 
             chunks_list.push(Chunk { chunk_id, compressed_size, uncompressed_size });
+            Ok(())
         };
 
-        let chunk_offset_parts = ["unknown1", "unknown2", "chunk_id", "compressed_size", "uncompressed_size"].to_vec();
-        parse_vector(input, start_offset + chunks_offset as u32, &chunk_offset_parts, &mut parse_single_chunk);
+        parse_vector(input, start_offset + chunks_offset, &mut parse_single_chunk)?;
 
         bundles.push(Bundle { bundle_id, chunks: chunks_list });
+        Ok(())
     };
 
-    let bundle_offset_parts = ["bundle_id", "chunks", "unknown", "header_size"].to_vec();
-    parse_vector(input, bundles_start, &bundle_offset_parts, &mut parse_single_bundle);
+    parse_vector(input, bundles_start, &mut parse_single_bundle)?;
 
-    bundles
+    Ok(bundles)
 }
 
-fn languages(input: &[u8], languages_start: u32) -> Vec<Language> {
+fn languages(input: &[u8], languages_start: u32) -> Result<Vec<Language>, Error> {
     let mut languages = Vec::<Language>::new();
 
-    let mut parse_single_language = |start_offset: u32, entry_offsets: HashMap<String, u16>| {
-        let name_offset = entry_offsets.get("name_offset").unwrap().to_owned() as u32;
-        let name_position = crate::parse_single!(le_u32, &input[(start_offset + name_offset as u32) as usize..]);
-        let name_data_offset = start_offset + name_offset + name_position;
-        let name_length = crate::parse_single!(le_u32, &input[name_data_offset as usize..]);
-        let name =
-            String::from_utf8_lossy(&input[(name_data_offset + 4) as usize..(name_data_offset + 4 + name_length) as usize]).into_owned();
+    let mut parse_single_language = |start_offset: u32, entry_offsets: [u16; 3]| {
+        let name_offset = entry_offsets[LanguageField::NameOffset as usize] as u32;
+        let name_slot = start_offset + name_offset;
+        let name = read_str(input, name_slot + read_u32(input, name_slot)?)?;
 
-        let language_id_offset = entry_offsets.get("language_id").unwrap().to_owned();
-        let language_id = crate::parse_single!(le_u8, &input[(start_offset + language_id_offset as u32) as usize..]);
+        let id = read_u8(input, start_offset + entry_offsets[LanguageField::LanguageId as usize] as u32)?;
 
-        languages.push(Language { id: language_id, name });
+        languages.push(Language { id, name });
+        Ok(())
     };
 
-    let languages_offset_parts = ["name_offset", "unknown1", "language_id"].to_vec();
-    parse_vector(input, languages_start, &languages_offset_parts, &mut parse_single_language);
+    parse_vector(input, languages_start, &mut parse_single_language)?;
 
-    languages
+    Ok(languages)
 }
 
-fn directories(input: &[u8], directories_start: u32) -> Vec<Directory> {
+fn directories(input: &[u8], directories_start: u32) -> Result<Vec<Directory>, Error> {
     let mut directories = Vec::<Directory>::new();
 
-    let mut parse_single_directory = |start_offset: u32, entry_offsets: HashMap<String, u16>| {
-        let name_offset = entry_offsets.get("name_offset").unwrap().to_owned() as u32;
-        let name_position = crate::parse_single!(le_u32, &input[(start_offset + name_offset as u32) as usize..]);
-        let name_data_offset = start_offset + name_offset + name_position;
-        let name_length = crate::parse_single!(le_u32, &input[name_data_offset as usize..]);
-        let name =
-            String::from_utf8_lossy(&input[(name_data_offset + 4) as usize..(name_data_offset + 4 + name_length) as usize]).into_owned();
-
-        let directory_id_offset = entry_offsets.get("directory_id").unwrap().to_owned();
-        let directory_id = if directory_id_offset > 0 {
-            crate::parse_single!(le_u64, &input[(start_offset + directory_id_offset as u32) as usize..])
-        } else {
-            0
-        };
+    let mut parse_single_directory = |start_offset: u32, entry_offsets: [u16; 5]| {
+        let name_offset = entry_offsets[DirectoryField::NameOffset as usize] as u32;
+        let name_slot = start_offset + name_offset;
+        let name = read_str(input, name_slot + read_u32(input, name_slot)?)?;
 
-        let parent_id_offset = entry_offsets.get("parent_id").unwrap().to_owned();
-        let parent_id = if parent_id_offset > 0 {
-            crate::parse_single!(le_u64, &input[(start_offset + parent_id_offset as u32) as usize..])
-        } else {
-            0
-        };
+        let directory_id_offset = entry_offsets[DirectoryField::DirectoryId as usize];
+        let id = if directory_id_offset > 0 { read_u64(input, start_offset + directory_id_offset as u32)? } else { 0 };
+
+        let parent_id_offset = entry_offsets[DirectoryField::ParentId as usize];
+        let parent_id = if parent_id_offset > 0 { read_u64(input, start_offset + parent_id_offset as u32)? } else { 0 };
 
-        directories.push(Directory { id: directory_id, name, parent_id });
+        directories.push(Directory { id, name, parent_id });
+        Ok(())
     };
 
-    let directory_offset_parts = ["unknown1", "unknown2", "directory_id", "parent_id", "name_offset"].to_vec();
-    parse_vector(input, directories_start, &directory_offset_parts, &mut parse_single_directory);
+    parse_vector(input, directories_start, &mut parse_single_directory)?;
 
-    directories
+    Ok(directories)
 }
 
-fn files(input: &[u8], files_start: u32) -> Vec<FileEntry> {
+fn files(input: &[u8], files_start: u32) -> Result<Vec<FileEntry>, Error> {
     let mut files = Vec::<FileEntry>::new();
 
-    let mut parse_single_file = |start_offset: u32, entry_offsets: HashMap<String, u16>| {
-        let name_offset = entry_offsets.get("name_offset").unwrap().to_owned() as u32;
-        let name_position = crate::parse_single!(le_u32, &input[(start_offset + name_offset as u32) as usize..]);
-        let name_data_offset = start_offset + name_offset + name_position;
-        let name_length = crate::parse_single!(le_u32, &input[name_data_offset as usize..]);
-        let name =
-            String::from_utf8_lossy(&input[(name_data_offset + 4) as usize..(name_data_offset + 4 + name_length) as usize]).into_owned();
-
-        let symlink_offset = entry_offsets.get("symlink_offset").unwrap().to_owned() as u32;
-        let symlink_position = crate::parse_single!(le_u32, &input[(start_offset + symlink_offset as u32) as usize..]);
-        let symlink_data_offset = start_offset + symlink_offset + symlink_position;
-        let symlink_length = crate::parse_single!(le_u32, &input[symlink_data_offset as usize..]);
-        let symlink =
-            String::from_utf8_lossy(&input[(symlink_data_offset + 4) as usize..(symlink_data_offset + 4 + symlink_length) as usize])
-                .into_owned();
+    let mut parse_single_file = |start_offset: u32, entry_offsets: [u16; 15]| {
+        let name_offset = entry_offsets[FileField::NameOffset as usize] as u32;
+        let name_slot = start_offset + name_offset;
+        let name = read_str(input, name_slot + read_u32(input, name_slot)?)?;
 
-        let file_id_offset = entry_offsets.get("file_id").unwrap().to_owned() as u32;
-        let file_id = crate::parse_single!(le_u64, &input[(start_offset + file_id_offset as u32) as usize..]);
+        let symlink_offset = entry_offsets[FileField::SymlinkOffset as usize] as u32;
+        let symlink_slot = start_offset + symlink_offset;
+        let symlink = read_str(input, symlink_slot + read_u32(input, symlink_slot)?)?;
 
-        let directory_id_offset = entry_offsets.get("directory_id").unwrap().to_owned() as u32;
-        let directory_id = crate::parse_single!(le_u64, &input[(start_offset + directory_id_offset as u32) as usize..]);
-
-        let file_size_id_offset = entry_offsets.get("file_size").unwrap().to_owned() as u32;
-        let file_size = crate::parse_single!(le_u32, &input[(start_offset + file_size_id_offset as u32) as usize..]);
-
-        let language_mask_offset = entry_offsets.get("language_mask").unwrap().to_owned() as u32;
-        let language_mask = crate::parse_single!(le_u32, &input[(start_offset + language_mask_offset as u32) as usize..]);
+        let file_id = read_u64(input, start_offset + entry_offsets[FileField::FileId as usize] as u32)?;
+        let directory_id = read_u64(input, start_offset + entry_offsets[FileField::DirectoryId as usize] as u32)?;
+        let file_size = read_u32(input, start_offset + entry_offsets[FileField::FileSize as usize] as u32)?;
+        let language_mask = read_u32(input, start_offset + entry_offsets[FileField::LanguageMask as usize] as u32)?;
 
         let mut chunks = Vec::<u64>::new();
-        let chunks_offset = start_offset + entry_offsets.get("chunks").unwrap().to_owned() as u32;
+        let chunks_offset = start_offset + entry_offsets[FileField::Chunks as usize] as u32;
 
         let mut append_to_chunks = |v: u64| {
             chunks.push(v);
+            Ok(())
         };
 
-        parse_long_vector(input, chunks_offset, &mut append_to_chunks);
+        parse_long_vector(input, chunks_offset, &mut append_to_chunks)?;
 
-        files.push(FileEntry { id: file_id, name, symlink, directory_id, language: language_mask, size: file_size, chunk_ids: chunks })
+        files.push(FileEntry { id: file_id, name, symlink, directory_id, language: language_mask, size: file_size, chunk_ids: chunks });
+        Ok(())
     };
 
-    let files_offset_parts = [
-        "unknown1",
-        "chunks",
-        "file_id",
-        "directory_id",
-        "file_size",
-        "name_offset",
-        "language_mask",
-        "unknown2",
-        "unknown3",
-        "unknown4",
-        "unknown5",
-        "symlink_offset",
-        "unknown6",
-        "unknown7",
-        "unknown8",
-    ]
-    .to_vec();
-
-    parse_vector(input, files_start, &files_offset_parts, &mut parse_single_file);
-
-    files
+    parse_vector(input, files_start, &mut parse_single_file)?;
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_file() -> File {
+        let header = Header {
+            magic: "RMAN".to_owned(),
+            major: 2,
+            minor: 0,
+            unknown: 0,
+            signature_type: 0,
+            offset: 28,
+            length: 0,
+            manifest_id: 0x1122_3344_5566_7788,
+            decompressed_length: 0,
+        };
+
+        let body = Body {
+            bundles: vec![Bundle { bundle_id: 1, chunks: vec![Chunk { compressed_size: 10, uncompressed_size: 20, chunk_id: 42 }] }],
+            languages: vec![Language { id: 0, name: "en_US".to_owned() }],
+            directories: vec![Directory { id: 1, parent_id: 0, name: "assets".to_owned() }],
+            files: vec![FileEntry {
+                id: 100,
+                name: "file.bin".to_owned(),
+                symlink: String::new(),
+                directory_id: 1,
+                size: 20,
+                language: 1,
+                chunk_ids: vec![42],
+            }],
+        };
+
+        File::from_parts(header, body)
+    }
+
+    #[test]
+    fn write_then_parse_round_trips() {
+        let original = sample_file();
+        let bytes = write(&original);
+        let first = parse(&bytes).expect("parse should succeed on freshly serialized bytes");
+
+        let rewritten = write(&first);
+        let second = parse(&rewritten).expect("re-parse should succeed");
+
+        assert_eq!(first, second);
+    }
 }
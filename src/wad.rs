@@ -1,5 +1,4 @@
 use nom::{
-    bytes::complete::tag,
     error::VerboseError,
     number::complete::{le_u16, le_u32, le_u64, le_u8},
     sequence::tuple,
@@ -7,6 +6,11 @@ use nom::{
 
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct File {
@@ -14,6 +18,14 @@ pub struct File {
     content: Vec<Content>,
 }
 
+impl File {
+    /// Every entry's metadata, in on-disk order. Pass one to [`extract`] or [`read_entry`] to
+    /// get at its decompressed bytes.
+    pub fn content(&self) -> &[Content] {
+        &self.content
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, EnumDiscriminants)]
 enum HeaderVersion {
     V1 { entry_offset: u16, entry_size: u16 },
@@ -30,7 +42,7 @@ struct Header {
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
-struct Content {
+pub struct Content {
     hash: u64,
     data_offset: u32,
     compressed_size: u32,
@@ -39,100 +51,642 @@ struct Content {
     version: ContentVersion,
 }
 
+impl Content {
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn data_offset(&self) -> u32 {
+        self.data_offset
+    }
+
+    pub fn compressed_size(&self) -> u32 {
+        self.compressed_size
+    }
+
+    pub fn uncompressed_size(&self) -> u32 {
+        self.uncompressed_size
+    }
+
+    pub fn compression_type(&self) -> CompressionType {
+        self.compression_type
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, EnumDiscriminants)]
 enum ContentVersion {
     V1 {},
     V2 { is_duplicate: bool, sha256: u64 },
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, FromPrimitive)]
-enum CompressionType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, FromPrimitive)]
+pub enum CompressionType {
     NONE = 0,
     GZIP = 1,
     REFERENCE = 2,
     ZSTD = 3,
 }
 
-pub fn parse(input: &[u8]) -> File {
-    let header = header(input);
-    let content = content(input, header.major, header.file_count);
+/// Errors produced while parsing or extracting a WAD archive.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("input is too short to contain a WAD header or entry table")]
+    Truncated,
+    #[error("input does not start with the RW magic")]
+    InvalidMagic,
+    #[error("unsupported WAD major version {0}")]
+    UnsupportedVersion(u8),
+    #[error("unknown compression type {0}")]
+    UnknownCompressionType(u8),
+    #[error(transparent)]
+    Extract(#[from] ExtractError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub fn parse(input: &[u8]) -> Result<File, Error> {
+    let header = header(input)?;
+    let content = content(input, header.major, header.file_count)?;
 
-    File { header, content }
+    Ok(File { header, content })
 }
 
-fn header(input: &[u8]) -> Header {
-    let (_, major, minor) = crate::parse_tuple!((tag("RW"), le_u8, le_u8), input);
+/// Parses just the header and entry table from a `Read + Seek` source, without reading any entry
+/// payloads, so a single file can be pulled out of a multi-gigabyte archive via [`read_entry`]
+/// without mapping the whole thing into memory.
+pub fn parse_stream<R: Read + Seek>(reader: &mut R) -> Result<File, Error> {
+    let mut magic_and_version = [0u8; 4];
+    reader.read_exact(&mut magic_and_version)?;
+
+    if &magic_and_version[0..2] != b"RW" {
+        return Err(Error::InvalidMagic);
+    }
+
+    let major = magic_and_version[2];
+    let minor = magic_and_version[3];
+
+    let header = match major {
+        1 => {
+            let mut rest = [0u8; 8];
+            reader.read_exact(&mut rest)?;
+            let entry_offset = u16::from_le_bytes([rest[0], rest[1]]);
+            let entry_size = u16::from_le_bytes([rest[2], rest[3]]);
+            let file_count = u32::from_le_bytes(rest[4..8].try_into().unwrap());
+            Header { major, minor, file_count, version: HeaderVersion::V1 { entry_offset, entry_size } }
+        }
+        2 => {
+            let mut ecdsa_length = [0u8; 1];
+            reader.read_exact(&mut ecdsa_length)?;
+            let ecdsa_length = ecdsa_length[0];
+
+            let mut ecdsa = vec![0u8; ecdsa_length as usize];
+            reader.read_exact(&mut ecdsa)?;
+            reader.seek(SeekFrom::Current(83 - ecdsa_length as i64))?;
+
+            let mut rest = [0u8; 16];
+            reader.read_exact(&mut rest)?;
+            let file_checksum = u64::from_le_bytes(rest[0..8].try_into().unwrap());
+            let entry_offset = u16::from_le_bytes(rest[8..10].try_into().unwrap());
+            let entry_size = u16::from_le_bytes(rest[10..12].try_into().unwrap());
+            let file_count = u32::from_le_bytes(rest[12..16].try_into().unwrap());
+            Header { major, minor, file_count, version: HeaderVersion::V2 { ecdsa, file_checksum, entry_offset, entry_size } }
+        }
+        3 => {
+            let mut ecdsa = vec![0u8; 256];
+            reader.read_exact(&mut ecdsa)?;
+
+            let mut rest = [0u8; 12];
+            reader.read_exact(&mut rest)?;
+            let file_checksum = u64::from_le_bytes(rest[0..8].try_into().unwrap());
+            let file_count = u32::from_le_bytes(rest[8..12].try_into().unwrap());
+            Header { major, minor, file_count, version: HeaderVersion::V3 { ecdsa, file_checksum } }
+        }
+        other => return Err(Error::UnsupportedVersion(other)),
+    };
+
+    let (data_start, entry_size) = entry_table_layout(header.major)?;
+    reader.seek(SeekFrom::Start(data_start as u64))?;
+
+    let mut content = Vec::with_capacity(header.file_count as usize);
+    for _ in 0..header.file_count {
+        let mut entry_buf = vec![0u8; entry_size as usize];
+        reader.read_exact(&mut entry_buf)?;
+        content.push(parse_content_entry(&entry_buf, header.major)?);
+    }
+
+    Ok(File { header, content })
+}
+
+/// Reads and decompresses a single entry on demand, seeking to `data_offset` and reading only
+/// `compressed_size` bytes rather than requiring the whole archive in memory.
+pub fn read_entry<R: Read + Seek>(reader: &mut R, content: &Content) -> Result<Extracted, Error> {
+    reader.seek(SeekFrom::Start(content.data_offset as u64))?;
+    let mut buf = vec![0u8; content.compressed_size as usize];
+    reader.read_exact(&mut buf)?;
+
+    Ok(decompress(&buf, content)?)
+}
+
+fn header(input: &[u8]) -> Result<Header, Error> {
+    if input.len() < 4 {
+        return Err(Error::Truncated);
+    }
+
+    if &input[0..2] != b"RW" {
+        return Err(Error::InvalidMagic);
+    }
+    let major = input[2];
+    let minor = input[3];
 
     if major == 1 {
+        if input.len() < 12 {
+            return Err(Error::Truncated);
+        }
         let (entry_offset, entry_size, file_count) = crate::parse_tuple!((le_u16, le_u16, le_u32), &input[4..]);
-        return Header { major, minor, file_count, version: HeaderVersion::V1 { entry_offset, entry_size } };
+        return Ok(Header { major, minor, file_count, version: HeaderVersion::V1 { entry_offset, entry_size } });
     }
 
     if major == 2 {
+        if input.len() < 5 {
+            return Err(Error::Truncated);
+        }
         let ecdsa_length = crate::parse_single!(le_u8, input);
-        let ecdsa = input[5..5 + ecdsa_length as usize].to_vec();
         let ecdsa_end = 5 + 83;
 
+        if input.len() < 5 + ecdsa_length as usize || input.len() < ecdsa_end + 16 {
+            return Err(Error::Truncated);
+        }
+        let ecdsa = input[5..5 + ecdsa_length as usize].to_vec();
+
         let (file_checksum, entry_offset, entry_size, file_count) =
-            crate::parse_tuple!((le_u64, le_u16, le_u16, le_u32), &input[ecdsa_end as usize..]);
+            crate::parse_tuple!((le_u64, le_u16, le_u16, le_u32), &input[ecdsa_end..]);
 
-        return Header { major, minor, file_count, version: HeaderVersion::V2 { ecdsa, file_checksum, entry_offset, entry_size } };
+        return Ok(Header { major, minor, file_count, version: HeaderVersion::V2 { ecdsa, file_checksum, entry_offset, entry_size } });
     }
 
     if major == 3 {
+        if input.len() < 4 + 256 + 12 {
+            return Err(Error::Truncated);
+        }
         let ecdsa = input[4..4 + 256_usize].to_vec();
         let (file_checksum, file_count) = crate::parse_tuple!((le_u64, le_u32), &input[4 + 256_usize..]);
-        return Header { major, minor, file_count, version: HeaderVersion::V3 { ecdsa, file_checksum } };
+        return Ok(Header { major, minor, file_count, version: HeaderVersion::V3 { ecdsa, file_checksum } });
     }
 
-    panic!("Invalid major version for wad file");
+    Err(Error::UnsupportedVersion(major))
 }
 
-fn content(input: &[u8], major: u8, file_count: u32) -> Vec<Content> {
-    let (data_start, entry_size) = match major {
-        1 => (4 + 2 + 2 + 4, 24),
-        2 => (4 + 1 + 83 + 8 + 2 + 2 + 4, 32),
-        3 => (4 + 256 + 8 + 4, 32),
-        _ => {
-            unreachable!();
-        }
-    };
+/// Returns `(data_start, entry_size)` for the fixed-size entry table of a given major version.
+fn entry_table_layout(major: u8) -> Result<(u32, u32), Error> {
+    match major {
+        1 => Ok((4 + 2 + 2 + 4, 24)),
+        2 => Ok((4 + 1 + 83 + 8 + 2 + 2 + 4, 32)),
+        3 => Ok((4 + 256 + 8 + 4, 32)),
+        other => Err(Error::UnsupportedVersion(other)),
+    }
+}
+
+/// Parses a single fixed-size entry record already sliced out of the entry table.
+fn parse_content_entry(input: &[u8], major: u8) -> Result<Content, Error> {
+    let (hash, data_offset, compressed_size, uncompressed_size) = crate::parse_tuple!((le_u64, le_u32, le_u32, le_u32), input);
+
+    let compression_value: u8 =
+        if major == 1 { crate::parse_single!(le_u32, &input[20..]) as u8 } else { crate::parse_single!(le_u8, &input[20..]) };
+    let compression_type =
+        CompressionType::from_u8(compression_value).ok_or(Error::UnknownCompressionType(compression_value))?;
+
+    if major == 1 {
+        return Ok(Content { hash, data_offset, compressed_size, uncompressed_size, compression_type, version: ContentVersion::V1 {} });
+    }
+
+    let (duplicate, _, sha256) = crate::parse_tuple!((le_u8, le_u16, le_u64), &input[21..]);
+    Ok(Content {
+        hash,
+        data_offset,
+        compressed_size,
+        uncompressed_size,
+        compression_type,
+        version: ContentVersion::V2 { is_duplicate: duplicate > 0, sha256 },
+    })
+}
+
+fn content(input: &[u8], major: u8, file_count: u32) -> Result<Vec<Content>, Error> {
+    let (data_start, entry_size) = entry_table_layout(major)?;
 
     let mut entries = Vec::<Content>::new();
 
     for offset_multiplier in 0..file_count {
-        let entry_offset = data_start + (offset_multiplier * entry_size);
-        let (hash, data_offset, compressed_size, uncompressed_size) =
-            crate::parse_tuple!((le_u64, le_u32, le_u32, le_u32), &input[entry_offset as usize..]);
-        let compression_value: u8 = if major == 1 {
-            crate::parse_single!(le_u32, &input[(entry_offset + 20) as usize..]) as u8
+        let entry_offset = (data_start + (offset_multiplier * entry_size)) as usize;
+        if input.len() < entry_offset + entry_size as usize {
+            return Err(Error::Truncated);
+        }
+
+        entries.push(parse_content_entry(&input[entry_offset..entry_offset + entry_size as usize], major)?);
+    }
+
+    Ok(entries)
+}
+
+/// The payload of a single `Content` entry once decompressed.
+#[derive(Debug, Clone)]
+pub enum Extracted {
+    /// Raw file bytes, ready to be written out.
+    Data(Vec<u8>),
+    /// A `REFERENCE` entry: the name of another logical file this one redirects to.
+    Reference(String),
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    Io(std::io::Error),
+    InvalidReference(std::str::Utf8Error),
+    SizeMismatch { hash: u64, expected: u32, actual: usize },
+    /// The entry's `data_offset`/`compressed_size` extend past the end of the archive bytes.
+    OutOfBounds { hash: u64, offset: u32, size: u32, input_len: usize },
+}
+
+impl std::fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractError::Io(e) => write!(f, "io error: {}", e),
+            ExtractError::InvalidReference(e) => write!(f, "reference entry is not valid utf-8: {}", e),
+            ExtractError::SizeMismatch { hash, expected, actual } => {
+                write!(f, "entry {:016x}: decompressed {} bytes but uncompressed_size is {}", hash, actual, expected)
+            }
+            ExtractError::OutOfBounds { hash, offset, size, input_len } => {
+                write!(f, "entry {:016x}: data range {}..{} exceeds input length {}", hash, offset, *offset as u64 + *size as u64, input_len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+impl From<std::io::Error> for ExtractError {
+    fn from(e: std::io::Error) -> Self {
+        ExtractError::Io(e)
+    }
+}
+
+/// Decompresses a single `Content` entry's payload out of the full archive bytes.
+pub fn extract(input: &[u8], content: &Content) -> Result<Extracted, ExtractError> {
+    let start = content.data_offset as usize;
+    let end = start + content.compressed_size as usize;
+
+    if end > input.len() {
+        return Err(ExtractError::OutOfBounds {
+            hash: content.hash,
+            offset: content.data_offset,
+            size: content.compressed_size,
+            input_len: input.len(),
+        });
+    }
+
+    decompress(&input[start..end], content)
+}
+
+/// Decompresses an entry's already-sliced-out compressed bytes according to its `compression_type`.
+fn decompress(slice: &[u8], content: &Content) -> Result<Extracted, ExtractError> {
+    if let CompressionType::REFERENCE = content.compression_type {
+        let path = std::str::from_utf8(slice).map_err(ExtractError::InvalidReference)?;
+        return Ok(Extracted::Reference(path.to_owned()));
+    }
+
+    let data = match content.compression_type {
+        CompressionType::NONE => slice.to_vec(),
+        CompressionType::GZIP => {
+            let mut decoder = flate2::read::GzDecoder::new(slice);
+            let mut out = Vec::new();
+            decoder.read_to_end(&mut out)?;
+            out
+        }
+        CompressionType::ZSTD => zstd::decode_all(slice)?,
+        CompressionType::REFERENCE => unreachable!(),
+    };
+
+    if data.len() != content.uncompressed_size as usize {
+        return Err(ExtractError::SizeMismatch { hash: content.hash, expected: content.uncompressed_size, actual: data.len() });
+    }
+
+    Ok(Extracted::Data(data))
+}
+
+/// Extracts every entry in `file` to `out_dir`, named by lowercase hex hash until real names are known.
+///
+/// Entries that share a `data_offset` (the V2 `is_duplicate` case) are only decompressed once.
+pub fn extract_all(file: &File, input: &[u8], out_dir: &Path) -> Result<(), ExtractError> {
+    extract_all_named(file, input, out_dir, None)
+}
+
+/// Like [`extract_all`], but resolves each entry's hash to a real relative path via `names`
+/// (creating intermediate directories) and falls back to the hex hash when a name is unknown.
+pub fn extract_all_named(file: &File, input: &[u8], out_dir: &Path, names: Option<&Names>) -> Result<(), ExtractError> {
+    fs::create_dir_all(out_dir)?;
+
+    let mut cache = HashMap::<u32, Extracted>::new();
+
+    for entry in &file.content {
+        let extracted = match cache.get(&entry.data_offset) {
+            Some(cached) => cached.clone(),
+            None => {
+                let result = extract(input, entry)?;
+                cache.insert(entry.data_offset, result.clone());
+                result
+            }
+        };
+
+        let resolved = names.and_then(|n| n.resolve(entry.hash));
+
+        match extracted {
+            Extracted::Data(data) => {
+                let path = match resolved {
+                    Some(relative) => out_dir.join(relative),
+                    None => out_dir.join(format!("{:016x}", entry.hash)),
+                };
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, data)?;
+            }
+            Extracted::Reference(target) => {
+                let name = resolved.map(str::to_owned).unwrap_or_else(|| format!("{:016x}", entry.hash));
+                let path = out_dir.join(format!("{}.redirect", name));
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, target)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves `Content.hash` back to the in-game path it was derived from.
+///
+/// WAD hashes are `xxh64(path.to_lowercase())` over a forward-slashed path, so a list of known
+/// paths (as used by RustedIronRing's names-file workflow) can be hashed once and looked up by
+/// hash for every extracted entry.
+pub struct Names {
+    by_hash: HashMap<u64, String>,
+}
+
+impl Names {
+    /// Builds a lookup table from a newline-delimited list of known paths.
+    pub fn load(list: &str) -> Self {
+        let by_hash = list
+            .lines()
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(|path| (xxhash_rust::xxh64::xxh64(path.to_lowercase().as_bytes(), 0), path.to_owned()))
+            .collect();
+
+        Names { by_hash }
+    }
+
+    pub fn resolve(&self, hash: u64) -> Option<&str> {
+        self.by_hash.get(&hash).map(String::as_str)
+    }
+
+    /// Returns `(resolved, total)` entry counts for `file`, to measure name-list coverage.
+    pub fn coverage(&self, file: &File) -> (usize, usize) {
+        let resolved = file.content.iter().filter(|entry| self.by_hash.contains_key(&entry.hash)).count();
+        (resolved, file.content.len())
+    }
+}
+
+/// Why a single entry or whole-archive checksum check failed.
+#[derive(Debug)]
+pub enum Mismatch {
+    /// A V2 entry's stored `sha256` (truncated to the first 8 bytes) didn't match its data.
+    EntryChecksum { expected: u64, actual: u64 },
+    /// The header's `file_checksum` didn't match the recomputed checksum of the whole archive.
+    FileChecksum { expected: u64, actual: u64 },
+    /// The entry's `data_offset`/`compressed_size` extend past the end of the archive bytes.
+    OutOfBounds { offset: u32, size: u32, input_len: usize },
+}
+
+/// Result of [`verify`]: every entry (plus the whole-file check under hash `0`) that passed, and
+/// every one that didn't along with why.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub ok: Vec<u64>,
+    pub failed: Vec<(u64, Mismatch)>,
+}
+
+/// Byte offset of the 8-byte `file_checksum` field within a V2/V3 header. `Builder::build` hashes
+/// the archive with this field still zeroed before patching in the real value, so `verify` has to
+/// blank the same bytes before recomputing the hash to match.
+fn file_checksum_offset(major: u8) -> Option<usize> {
+    match major {
+        2 => Some(4 + 1 + 83),
+        3 => Some(4 + 256),
+        _ => None,
+    }
+}
+
+/// Recomputes every checksum the WAD format records and compares it against the stored value,
+/// collecting pass/fail per entry rather than aborting on the first mismatch.
+pub fn verify(input: &[u8], file: &File) -> VerifyReport {
+    let mut report = VerifyReport::default();
+
+    let file_checksum = match &file.header.version {
+        HeaderVersion::V1 { .. } => None,
+        HeaderVersion::V2 { file_checksum, .. } => Some(*file_checksum),
+        HeaderVersion::V3 { file_checksum, .. } => Some(*file_checksum),
+    };
+
+    if let Some(expected) = file_checksum {
+        let mut hashed = input.to_vec();
+        if let Some(offset) = file_checksum_offset(file.header.major).filter(|&offset| offset + 8 <= hashed.len()) {
+            hashed[offset..offset + 8].fill(0);
+        }
+
+        let actual = xxhash_rust::xxh64::xxh64(&hashed, 0);
+        if actual == expected {
+            report.ok.push(0);
         } else {
-            crate::parse_single!(le_u8, &input[(entry_offset + 20) as usize..])
+            report.failed.push((0, Mismatch::FileChecksum { expected, actual }));
+        }
+    }
+
+    for entry in &file.content {
+        let expected = match &entry.version {
+            ContentVersion::V1 {} => continue,
+            ContentVersion::V2 { sha256, .. } => *sha256,
         };
-        let compression_type = CompressionType::from_u8(compression_value).unwrap();
-
-        if major == 1 {
-            entries.push(Content {
-                hash,
-                data_offset,
-                compressed_size,
-                uncompressed_size,
-                compression_type,
-                version: ContentVersion::V1 {},
-            });
-            continue;
-        }
-
-        let (duplicate, _, sha256) = crate::parse_tuple!((le_u8, le_u16, le_u64), &input[(entry_offset + 21) as usize..]);
-        entries.push(Content {
-            hash,
-            data_offset,
-            compressed_size,
-            uncompressed_size,
-            compression_type,
-            version: ContentVersion::V2 { is_duplicate: duplicate > 0, sha256 },
-        });
+
+        let start = entry.data_offset as usize;
+        let end = match start.checked_add(entry.compressed_size as usize) {
+            Some(end) if end <= input.len() => end,
+            _ => {
+                report.failed.push((
+                    entry.hash,
+                    Mismatch::OutOfBounds { offset: entry.data_offset, size: entry.compressed_size, input_len: input.len() },
+                ));
+                continue;
+            }
+        };
+
+        let digest = Sha256::digest(&input[start..end]);
+        let actual = u64::from_le_bytes(digest[..8].try_into().unwrap());
+
+        if actual == expected {
+            report.ok.push(entry.hash);
+        } else {
+            report.failed.push((entry.hash, Mismatch::EntryChecksum { expected, actual }));
+        }
     }
 
-    entries
+    report
+}
+
+/// How a [`Builder`] entry's payload should be stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackCompression {
+    Store,
+    Gzip { level: u32 },
+    Zstd { level: i32 },
+}
+
+struct Blob {
+    compressed: Vec<u8>,
+    compression_type: CompressionType,
+    uncompressed_size: u32,
+    sha256: u64,
+}
+
+/// Builds a V3 WAD archive from a set of `(path, bytes)` pairs.
+#[derive(Default)]
+pub struct Builder {
+    entries: Vec<(String, Vec<u8>, PackCompression)>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    /// Queues a file for packing. `path` is hashed with the same `xxh64(lowercase)` scheme
+    /// [`Names`] uses to resolve entries on extraction.
+    pub fn add(&mut self, path: impl Into<String>, bytes: Vec<u8>, compression: PackCompression) -> &mut Self {
+        self.entries.push((path.into(), bytes, compression));
+        self
+    }
+
+    /// Serializes the queued entries into a valid V3 WAD. Byte-identical payloads are detected
+    /// and compressed only once, with later entries pointing at the same data region and their
+    /// `is_duplicate` flag set.
+    pub fn build(&self) -> Vec<u8> {
+        let mut blobs = Vec::<Blob>::new();
+        let mut blob_of_raw = HashMap::<&[u8], usize>::new();
+        let mut entries = Vec::with_capacity(self.entries.len());
+
+        for (path, raw, compression) in &self.entries {
+            let hash = xxhash_rust::xxh64::xxh64(path.to_lowercase().as_bytes(), 0);
+
+            let (blob_index, is_duplicate) = match blob_of_raw.get(raw.as_slice()) {
+                Some(&idx) => (idx, true),
+                None => {
+                    let idx = blobs.len();
+                    blobs.push(compress_entry(raw, *compression));
+                    blob_of_raw.insert(raw.as_slice(), idx);
+                    (idx, false)
+                }
+            };
+
+            entries.push((hash, blob_index, is_duplicate));
+        }
+
+        let file_count = entries.len() as u32;
+        let data_start = 4 + 256 + 8 + 4 + file_count * 32;
+
+        let mut blob_offsets = Vec::with_capacity(blobs.len());
+        let mut offset = data_start;
+        for blob in &blobs {
+            blob_offsets.push(offset);
+            offset += blob.compressed.len() as u32;
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RW");
+        out.push(3); // major
+        out.push(0); // minor
+        out.extend(std::iter::repeat_n(0u8, 256)); // ecdsa signature, unsigned
+        let file_checksum_pos = out.len();
+        out.extend_from_slice(&0u64.to_le_bytes()); // file_checksum, patched once the body is written
+        out.extend_from_slice(&file_count.to_le_bytes());
+
+        for (hash, blob_index, is_duplicate) in &entries {
+            let blob = &blobs[*blob_index];
+            out.extend_from_slice(&hash.to_le_bytes());
+            out.extend_from_slice(&blob_offsets[*blob_index].to_le_bytes());
+            out.extend_from_slice(&(blob.compressed.len() as u32).to_le_bytes());
+            out.extend_from_slice(&blob.uncompressed_size.to_le_bytes());
+            out.push(blob.compression_type as u8);
+            out.push(*is_duplicate as u8);
+            out.extend_from_slice(&0u16.to_le_bytes()); // padding
+            out.extend_from_slice(&blob.sha256.to_le_bytes());
+        }
+
+        for blob in &blobs {
+            out.extend_from_slice(&blob.compressed);
+        }
+
+        let file_checksum = xxhash_rust::xxh64::xxh64(&out, 0);
+        out[file_checksum_pos..file_checksum_pos + 8].copy_from_slice(&file_checksum.to_le_bytes());
+
+        out
+    }
+}
+
+fn compress_entry(raw: &[u8], compression: PackCompression) -> Blob {
+    let (compression_type, compressed) = match compression {
+        PackCompression::Store => (CompressionType::NONE, raw.to_vec()),
+        PackCompression::Gzip { level } => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::new(level));
+            encoder.write_all(raw).unwrap();
+            (CompressionType::GZIP, encoder.finish().unwrap())
+        }
+        PackCompression::Zstd { level } => (CompressionType::ZSTD, zstd::encode_all(raw, level).unwrap()),
+    };
+
+    let digest = Sha256::digest(&compressed);
+    let sha256 = u64::from_le_bytes(digest[..8].try_into().unwrap());
+
+    Blob { compressed, compression_type, uncompressed_size: raw.len() as u32, sha256 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_then_parse_round_trips() {
+        let mut builder = Builder::new();
+        builder.add("data/a.txt", b"hello world".to_vec(), PackCompression::Store);
+        builder.add("data/b.txt", b"hello world".to_vec(), PackCompression::Store); // duplicate content
+        builder.add("data/c.txt", b"some other bytes".to_vec(), PackCompression::Zstd { level: 3 });
+
+        let bytes = builder.build();
+        let parsed = parse(&bytes).expect("freshly built archive should parse");
+
+        assert_eq!(parsed.content.len(), 3);
+        assert_eq!(verify(&bytes, &parsed).failed.len(), 0, "a freshly built archive should pass its own verify()");
+
+        for (path, expected) in [("data/a.txt", b"hello world".as_slice()), ("data/c.txt", b"some other bytes".as_slice())] {
+            let hash = xxhash_rust::xxh64::xxh64(path.to_lowercase().as_bytes(), 0);
+            let entry = parsed.content.iter().find(|c| c.hash == hash).expect("entry should round-trip by hash");
+
+            match extract(&bytes, entry).expect("entry should extract cleanly") {
+                Extracted::Data(data) => assert_eq!(data, expected),
+                Extracted::Reference(_) => panic!("expected a data entry"),
+            }
+        }
+
+        let duplicate_hash = xxhash_rust::xxh64::xxh64("data/b.txt".to_lowercase().as_bytes(), 0);
+        let duplicate = parsed.content.iter().find(|c| c.hash == duplicate_hash).unwrap();
+        let original = parsed.content.iter().find(|c| c.hash == xxhash_rust::xxh64::xxh64("data/a.txt".to_lowercase().as_bytes(), 0)).unwrap();
+        assert_eq!(duplicate.data_offset, original.data_offset, "byte-identical entries should share one blob");
+    }
 }